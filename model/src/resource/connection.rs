@@ -0,0 +1,62 @@
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::tai::TaiTime;
+
+/// Staged or active transport parameters for a single IS-05 connection
+/// endpoint. The exact shape is transport- and leg-specific, so it is kept
+/// as opaque JSON; nothing in this crate currently validates it against the
+/// resource's transport before accepting a PATCH.
+#[derive(Debug, Clone, Default)]
+pub struct TransportParams(pub Value);
+
+/// How a staged transport-parameter change should be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationMode {
+    Immediate,
+    ScheduledRelative,
+    ScheduledAbsolute,
+}
+
+impl ActivationMode {
+    /// The wire value of this mode, as sent/received in `activation.mode`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActivationMode::Immediate => "activate_immediate",
+            ActivationMode::ScheduledRelative => "activate_scheduled_relative",
+            ActivationMode::ScheduledAbsolute => "activate_scheduled_absolute",
+        }
+    }
+}
+
+/// The most recently requested activation for an endpoint. Kept around
+/// (independent of whether it has fired yet) so `GET .../staged` can echo
+/// back `mode`/`requested_time`/`activation_time`, per IS-05.
+#[derive(Debug, Clone)]
+pub struct ActivationState {
+    pub mode: ActivationMode,
+    /// The raw `requested_time` the client sent, echoed back verbatim.
+    pub requested_time: Option<String>,
+    /// The absolute TAI instant this activation applies (or applied) at.
+    pub activation_time: TaiTime,
+}
+
+/// Staged/active state for one IS-05 connection endpoint (a sender or a
+/// receiver), mirroring the `staged`/`active` halves of the API.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionEndpoint {
+    pub master_enable: bool,
+    pub staged: TransportParams,
+    pub active: TransportParams,
+    /// The last activation requested via PATCH, if any.
+    pub activation: Option<ActivationState>,
+    /// When a still-armed scheduled activation should fire. `None` once it
+    /// has fired, been cancelled, or nothing was ever scheduled.
+    pub pending_deadline: Option<Instant>,
+}
+
+impl ConnectionEndpoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
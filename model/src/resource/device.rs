@@ -3,7 +3,7 @@ use uuid::Uuid;
 
 use crate::tai::TaiTime;
 
-use super::{Node, Resource};
+use super::{Node, Receiver, Resource, Sender};
 
 #[derive(Debug)]
 pub struct Device {
@@ -26,6 +26,8 @@ pub struct DeviceBuilder {
     label: Option<String>,
     type_: String,
     node_id: Uuid,
+    senders: Vec<Uuid>,
+    receivers: Vec<Uuid>,
 }
 
 impl DeviceBuilder {
@@ -34,6 +36,8 @@ impl DeviceBuilder {
             label: None,
             type_: device_type.into(),
             node_id: node.id,
+            senders: Vec::new(),
+            receivers: Vec::new(),
         }
     }
 
@@ -42,6 +46,20 @@ impl DeviceBuilder {
         self
     }
 
+    /// Back-link a sender to this device, so it shows up in
+    /// `Device::senders` (and so `Device::to_json`'s `senders` list).
+    pub fn attach_sender(mut self, sender: &Sender) -> DeviceBuilder {
+        self.senders.push(sender.id);
+        self
+    }
+
+    /// Back-link a receiver to this device, so it shows up in
+    /// `Device::receivers` (and so `Device::to_json`'s `receivers` list).
+    pub fn attach_receiver(mut self, receiver: &Receiver) -> DeviceBuilder {
+        self.receivers.push(receiver.id);
+        self
+    }
+
     pub fn build(self) -> Device {
         Device {
             id: Uuid::new_v4(),
@@ -49,8 +67,8 @@ impl DeviceBuilder {
             label: self.label.unwrap_or(String::new()),
             type_: self.type_,
             node_id: self.node_id,
-            senders: Vec::new(),
-            receivers: Vec::new(),
+            senders: self.senders,
+            receivers: self.receivers,
         }
     }
 }
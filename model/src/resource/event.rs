@@ -0,0 +1,13 @@
+use serde::Serialize;
+
+/// The value carried by an IS-07 event/tally message. Application code
+/// builds one of these and hands it to `Node::emit_event` to publish it to
+/// subscribers of the source.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum EventState {
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Enum(String),
+}
@@ -0,0 +1,38 @@
+use uuid::Uuid;
+
+/// Service type this node's own IS-04 Node API is advertised under, per
+/// AMWA IS-04.
+const SERVICE_NAME: &str = "_nmos-node._tcp";
+
+/// Advertises this node's own `_nmos-node._tcp` service over mDNS,
+/// including the `api_proto`/`api_ver`/`api_auth` TXT records registries
+/// and other nodes rely on for discovery. Dropping this withdraws the
+/// advertisement.
+pub struct NodeAdvertiser {
+    _responder: libmdns::Responder,
+    _service: libmdns::Service,
+}
+
+impl NodeAdvertiser {
+    /// Start advertising `node_id` on `port`. `api_proto` should be
+    /// `"https"` when the node is serving over TLS, `"http"` otherwise.
+    pub fn start(node_id: Uuid, port: u16, api_proto: &str) -> std::io::Result<Self> {
+        let responder = libmdns::Responder::new()?;
+
+        let service = responder.register(
+            SERVICE_NAME.to_owned(),
+            node_id.to_string(),
+            port,
+            &[
+                &format!("api_proto={api_proto}"),
+                "api_ver=v1.3",
+                "api_auth=false",
+            ],
+        );
+
+        Ok(Self {
+            _responder: responder,
+            _service: service,
+        })
+    }
+}
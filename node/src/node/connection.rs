@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use nmos_rs_model::resource::connection::{
+    ActivationMode, ActivationState, ConnectionEndpoint, TransportParams,
+};
+use nmos_rs_model::tai::TaiTime;
+use nmos_rs_model::Model;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tokio::sync::RwLock;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// How often the activation task checks for armed activations that have
+/// come due.
+const ACTIVATION_TICK: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResourceKind {
+    Sender,
+    Receiver,
+}
+
+impl ResourceKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ResourceKind::Sender => "senders",
+            ResourceKind::Receiver => "receivers",
+        }
+    }
+
+    /// The field name for this endpoint's reciprocal connection in the
+    /// IS-05 staged/active response body.
+    fn peer_id_field(self) -> &'static str {
+        match self {
+            ResourceKind::Sender => "receiver_id",
+            ResourceKind::Receiver => "sender_id",
+        }
+    }
+}
+
+/// Holds the IS-05 staged/active transport-parameter state for every sender
+/// and receiver, and drives scheduled activations.
+pub struct ConnectionManager {
+    model: Arc<Model>,
+    senders: RwLock<HashMap<Uuid, ConnectionEndpoint>>,
+    receivers: RwLock<HashMap<Uuid, ConnectionEndpoint>>,
+}
+
+impl ConnectionManager {
+    pub fn new(model: Arc<Model>) -> Arc<Self> {
+        Arc::new(Self {
+            model,
+            senders: RwLock::new(HashMap::new()),
+            receivers: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// The IS-05 `/x-nmos/connection/v1.1/single/...` router.
+    pub fn router(self: &Arc<Self>) -> Router {
+        Router::new()
+            .route(
+                "/x-nmos/connection/v1.1/single/senders/:id/staged",
+                get(get_sender_staged).patch(patch_sender_staged),
+            )
+            .route(
+                "/x-nmos/connection/v1.1/single/senders/:id/active",
+                get(get_sender_active),
+            )
+            .route(
+                "/x-nmos/connection/v1.1/single/receivers/:id/staged",
+                get(get_receiver_staged).patch(patch_receiver_staged),
+            )
+            .route(
+                "/x-nmos/connection/v1.1/single/receivers/:id/active",
+                get(get_receiver_active),
+            )
+            .with_state(self.clone())
+    }
+
+    /// Periodically fire any scheduled activations that have come due,
+    /// atomically swapping staged into active and bumping the resource's
+    /// IS-04 version.
+    pub async fn run_activations(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(ACTIVATION_TICK).await;
+
+            self.fire_due(ResourceKind::Sender).await;
+            self.fire_due(ResourceKind::Receiver).await;
+        }
+    }
+
+    async fn fire_due(&self, kind: ResourceKind) {
+        let now = Instant::now();
+        let mut map = self.map(kind).write().await;
+
+        for (id, endpoint) in map.iter_mut() {
+            let due = matches!(endpoint.pending_deadline, Some(deadline) if deadline <= now);
+
+            if due {
+                endpoint.active = endpoint.staged.clone();
+                endpoint.pending_deadline = None;
+                self.model.bump_version(kind.as_str(), *id).await;
+            }
+        }
+    }
+
+    fn map(&self, kind: ResourceKind) -> &RwLock<HashMap<Uuid, ConnectionEndpoint>> {
+        match kind {
+            ResourceKind::Sender => &self.senders,
+            ResourceKind::Receiver => &self.receivers,
+        }
+    }
+
+    /// Whether `id` actually names a sender/receiver in the `Model` —
+    /// `staged`/`active` are only meaningful for resources that exist.
+    async fn resource_exists(&self, kind: ResourceKind, id: Uuid) -> bool {
+        match kind {
+            ResourceKind::Sender => self.model.senders().await.contains_key(&id),
+            ResourceKind::Receiver => self.model.receivers().await.contains_key(&id),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StagedPatch {
+    #[serde(default)]
+    master_enable: Option<bool>,
+    #[serde(default)]
+    transport_params: Option<Value>,
+    #[serde(default)]
+    activation: Option<ActivationRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivationRequest {
+    /// `null` cancels any pending scheduled activation.
+    mode: Option<String>,
+    /// For `activate_scheduled_relative`, a `<seconds>:<nanoseconds>` offset
+    /// from now. For `activate_scheduled_absolute`, a TAI
+    /// `<seconds>:<nanoseconds>` timestamp.
+    #[serde(default)]
+    requested_time: Option<String>,
+}
+
+enum ConnectionError {
+    /// `id` doesn't name a sender/receiver this node knows about.
+    NotFound,
+    /// Activating with no staged transport-parameter change to apply.
+    NoStagedChange,
+    /// A scheduled absolute activation time that has already passed.
+    ActivationTimeInPast,
+    /// `activation.mode` wasn't one of the three modes the spec defines.
+    UnknownActivationMode,
+    /// `activation.requested_time` wasn't a valid `<seconds>:<nanoseconds>`
+    /// timestamp for the given mode.
+    InvalidRequestedTime,
+}
+
+impl IntoResponse for ConnectionError {
+    fn into_response(self) -> Response {
+        let (status, code, error) = match self {
+            ConnectionError::NotFound => (StatusCode::NOT_FOUND, 404, "resource not found"),
+            ConnectionError::NoStagedChange => {
+                (StatusCode::BAD_REQUEST, 400, "no staged change to activate")
+            }
+            ConnectionError::ActivationTimeInPast => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                500,
+                "requested activation time is in the past",
+            ),
+            ConnectionError::UnknownActivationMode => {
+                (StatusCode::BAD_REQUEST, 400, "invalid activation mode")
+            }
+            ConnectionError::InvalidRequestedTime => {
+                (StatusCode::BAD_REQUEST, 400, "invalid requested_time")
+            }
+        };
+
+        (
+            status,
+            Json(json!({ "code": code, "error": error, "debug": Value::Null })),
+        )
+            .into_response()
+    }
+}
+
+/// Build the IS-05 `sender_id`/`receiver_id`, `master_enable`,
+/// `transport_params`, `activation` response body for `/staged` or
+/// `/active`.
+fn endpoint_json(kind: ResourceKind, endpoint: &ConnectionEndpoint, transport_params: Value) -> Value {
+    let mut body = Map::new();
+
+    body.insert(kind.peer_id_field().to_string(), Value::Null);
+    body.insert(
+        "master_enable".to_string(),
+        Value::Bool(endpoint.master_enable),
+    );
+    body.insert("transport_params".to_string(), transport_params);
+    body.insert("activation".to_string(), activation_json(&endpoint.activation));
+
+    Value::Object(body)
+}
+
+fn activation_json(activation: &Option<ActivationState>) -> Value {
+    match activation {
+        Some(activation) => json!({
+            "mode": activation.mode.as_str(),
+            "requested_time": activation.requested_time,
+            "activation_time": activation.activation_time.to_string(),
+        }),
+        None => json!({
+            "mode": Value::Null,
+            "requested_time": Value::Null,
+            "activation_time": Value::Null,
+        }),
+    }
+}
+
+async fn get_staged(
+    manager: &ConnectionManager,
+    kind: ResourceKind,
+    id: Uuid,
+) -> Result<impl IntoResponse, ConnectionError> {
+    if !manager.resource_exists(kind, id).await {
+        return Err(ConnectionError::NotFound);
+    }
+
+    let map = manager.map(kind).read().await;
+    let endpoint = map.get(&id).cloned().unwrap_or_default();
+
+    Ok(Json(endpoint_json(kind, &endpoint, endpoint.staged.0.clone())))
+}
+
+async fn get_active(
+    manager: &ConnectionManager,
+    kind: ResourceKind,
+    id: Uuid,
+) -> Result<impl IntoResponse, ConnectionError> {
+    if !manager.resource_exists(kind, id).await {
+        return Err(ConnectionError::NotFound);
+    }
+
+    let map = manager.map(kind).read().await;
+    let endpoint = map.get(&id).cloned().unwrap_or_default();
+
+    Ok(Json(endpoint_json(kind, &endpoint, endpoint.active.0.clone())))
+}
+
+async fn patch_staged(
+    manager: &ConnectionManager,
+    kind: ResourceKind,
+    id: Uuid,
+    patch: StagedPatch,
+) -> Result<impl IntoResponse, ConnectionError> {
+    if !manager.resource_exists(kind, id).await {
+        return Err(ConnectionError::NotFound);
+    }
+
+    let mut map = manager.map(kind).write().await;
+    let endpoint = map.entry(id).or_insert_with(ConnectionEndpoint::new);
+
+    if let Some(master_enable) = patch.master_enable {
+        endpoint.master_enable = master_enable;
+    }
+
+    if let Some(transport_params) = patch.transport_params {
+        endpoint.staged = TransportParams(transport_params);
+        endpoint.pending_deadline = None;
+    }
+
+    if let Some(activation) = patch.activation {
+        let mode = match activation.mode.as_deref() {
+            Some("activate_immediate") => ActivationMode::Immediate,
+            Some("activate_scheduled_relative") => ActivationMode::ScheduledRelative,
+            Some("activate_scheduled_absolute") => ActivationMode::ScheduledAbsolute,
+            None => {
+                // A `mode` of null cancels any pending scheduled
+                // activation; it doesn't touch the last-requested
+                // activation info or the staged transport params.
+                endpoint.pending_deadline = None;
+                return Ok(Json(endpoint_json(kind, endpoint, endpoint.staged.0.clone())));
+            }
+            Some(_) => return Err(ConnectionError::UnknownActivationMode),
+        };
+
+        let has_staged_change = endpoint.staged.0 != Value::Null;
+
+        if !has_staged_change {
+            return Err(ConnectionError::NoStagedChange);
+        }
+
+        let (pending_deadline, activation_time) = match mode {
+            ActivationMode::Immediate => (Instant::now(), TaiTime::now()),
+            ActivationMode::ScheduledRelative => {
+                let offset = activation
+                    .requested_time
+                    .as_deref()
+                    .and_then(parse_tai_duration)
+                    .ok_or(ConnectionError::InvalidRequestedTime)?;
+
+                (Instant::now() + offset, TaiTime::now() + offset)
+            }
+            ActivationMode::ScheduledAbsolute => {
+                let requested_time = activation
+                    .requested_time
+                    .as_deref()
+                    .and_then(|t| t.parse::<TaiTime>().ok())
+                    .ok_or(ConnectionError::InvalidRequestedTime)?;
+
+                let offset = requested_time
+                    .checked_duration_since(TaiTime::now())
+                    .ok_or(ConnectionError::ActivationTimeInPast)?;
+
+                (Instant::now() + offset, requested_time)
+            }
+        };
+
+        endpoint.activation = Some(ActivationState {
+            mode,
+            requested_time: activation.requested_time,
+            activation_time,
+        });
+
+        match mode {
+            ActivationMode::Immediate => {
+                endpoint.active = endpoint.staged.clone();
+                endpoint.pending_deadline = None;
+            }
+            _ => {
+                endpoint.pending_deadline = Some(pending_deadline);
+            }
+        }
+    }
+
+    Ok(Json(endpoint_json(kind, endpoint, endpoint.staged.0.clone())))
+}
+
+/// Parse an `activate_scheduled_relative` `requested_time` of the form
+/// `<seconds>:<nanoseconds>` into the offset from now it describes.
+fn parse_tai_duration(requested: &str) -> Option<Duration> {
+    let (secs, nanos) = requested.split_once(':')?;
+    let secs: u64 = secs.parse().ok()?;
+    let nanos: u32 = nanos.parse().ok()?;
+
+    Some(Duration::new(secs, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tai_duration_parses_seconds_and_nanos() {
+        assert_eq!(
+            parse_tai_duration("5:500000000"),
+            Some(Duration::new(5, 500_000_000))
+        );
+        assert_eq!(parse_tai_duration("0:0"), Some(Duration::new(0, 0)));
+    }
+
+    #[test]
+    fn parse_tai_duration_rejects_malformed_input() {
+        assert_eq!(parse_tai_duration(""), None);
+        assert_eq!(parse_tai_duration("5"), None);
+        assert_eq!(parse_tai_duration("-5:0"), None);
+        assert_eq!(parse_tai_duration("5:notanumber"), None);
+    }
+}
+
+async fn get_sender_staged(
+    State(manager): State<Arc<ConnectionManager>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ConnectionError> {
+    get_staged(&manager, ResourceKind::Sender, id).await
+}
+
+async fn get_sender_active(
+    State(manager): State<Arc<ConnectionManager>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ConnectionError> {
+    get_active(&manager, ResourceKind::Sender, id).await
+}
+
+async fn patch_sender_staged(
+    State(manager): State<Arc<ConnectionManager>>,
+    Path(id): Path<Uuid>,
+    Json(patch): Json<StagedPatch>,
+) -> Result<impl IntoResponse, ConnectionError> {
+    patch_staged(&manager, ResourceKind::Sender, id, patch).await
+}
+
+async fn get_receiver_staged(
+    State(manager): State<Arc<ConnectionManager>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ConnectionError> {
+    get_staged(&manager, ResourceKind::Receiver, id).await
+}
+
+async fn get_receiver_active(
+    State(manager): State<Arc<ConnectionManager>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ConnectionError> {
+    get_active(&manager, ResourceKind::Receiver, id).await
+}
+
+async fn patch_receiver_staged(
+    State(manager): State<Arc<ConnectionManager>>,
+    Path(id): Path<Uuid>,
+    Json(patch): Json<StagedPatch>,
+) -> Result<impl IntoResponse, ConnectionError> {
+    patch_staged(&manager, ResourceKind::Receiver, id, patch).await
+}
@@ -0,0 +1,9 @@
+use nmos_rs_model::resource::event::EventState;
+use uuid::Uuid;
+
+/// Application hook for IS-07 event/tally sources. `current_state` supplies
+/// the mandatory initial snapshot sent to a client as soon as it subscribes
+/// to a source; ongoing updates are pushed separately via `Node::emit_event`.
+pub trait EventHandler: Send + Sync {
+    fn current_state(&self, source_id: Uuid) -> Option<EventState>;
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use nmos_rs_model::resource::event::EventState;
+use nmos_rs_model::tai::TaiTime;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::EventHandler;
+
+/// Per-subscriber queue depth. While a client that can't keep up has a full
+/// queue, new messages for it are dropped (the already-queued ones are kept)
+/// rather than letting it block emission for everyone else.
+const SUBSCRIBER_QUEUE_CAPACITY: usize = 16;
+
+/// How often each connection receives a heartbeat message, whether or not
+/// events are also being sent on it.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Fans IS-07 event/tally updates out to WebSocket subscribers, grouped by
+/// source ID. Each subscriber gets its own bounded queue so one slow
+/// consumer can't back-pressure the rest of the node.
+#[derive(Default)]
+pub struct EventHub {
+    event_handler: Option<Arc<dyn EventHandler>>,
+    subscribers: Mutex<HashMap<Uuid, Vec<mpsc::Sender<Message>>>>,
+}
+
+impl EventHub {
+    pub fn new(event_handler: Option<Arc<dyn EventHandler>>) -> Arc<Self> {
+        Arc::new(Self {
+            event_handler,
+            subscribers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn router(self: &Arc<Self>) -> Router {
+        Router::new()
+            .route("/x-nmos/events/v1.0/ws", get(ws_upgrade))
+            .with_state(self.clone())
+    }
+
+    /// Publish `state` for `source_id` to every current subscriber.
+    pub fn emit(&self, source_id: Uuid, state: EventState) {
+        let message = event_message(source_id, &state);
+        let mut subscribers = self.subscribers.lock().unwrap();
+
+        if let Some(queues) = subscribers.get_mut(&source_id) {
+            queues.retain(|tx| match tx.try_send(message.clone()) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => true,
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            });
+        }
+    }
+
+    fn subscribe(&self, source_id: Uuid) -> mpsc::Receiver<Message> {
+        let (tx, rx) = mpsc::channel(SUBSCRIBER_QUEUE_CAPACITY);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .entry(source_id)
+            .or_default()
+            .push(tx);
+        rx
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum ClientCommand {
+    Subscription { sources: Vec<Uuid> },
+}
+
+async fn ws_upgrade(
+    State(hub): State<Arc<EventHub>>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_socket(hub, socket))
+}
+
+async fn handle_socket(hub: Arc<EventHub>, mut socket: WebSocket) {
+    // Wait for the client's subscription request before sending anything.
+    let sources = loop {
+        match socket.recv().await {
+            Some(Ok(Message::Text(text))) => {
+                match serde_json::from_str::<ClientCommand>(&text) {
+                    Ok(ClientCommand::Subscription { sources }) => break sources,
+                    Err(_) => continue,
+                }
+            }
+            Some(Ok(_)) => continue,
+            _ => return,
+        }
+    };
+
+    let mut receivers = Vec::with_capacity(sources.len());
+
+    for source_id in &sources {
+        // Mandatory initial snapshot.
+        if let Some(handler) = &hub.event_handler {
+            if let Some(state) = handler.current_state(*source_id) {
+                if socket
+                    .send(event_message(*source_id, &state))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+
+        receivers.push(hub.subscribe(*source_id));
+    }
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                if socket.send(heartbeat_message()).await.is_err() {
+                    return;
+                }
+            }
+            message = next_from_any(&mut receivers) => {
+                match message {
+                    Some(message) => {
+                        if socket.send(message).await.is_err() {
+                            return;
+                        }
+                    }
+                    None => return,
+                }
+            }
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(_)) => continue,
+                    _ => return,
+                }
+            }
+        }
+    }
+}
+
+/// Poll every per-source receiver for this connection and return the first
+/// message available. Returns `None` as soon as any one of the receivers is
+/// closed.
+async fn next_from_any(receivers: &mut [mpsc::Receiver<Message>]) -> Option<Message> {
+    if receivers.is_empty() {
+        // No sources subscribed to; stall forever rather than busy-loop.
+        std::future::pending::<()>().await;
+    }
+
+    let (message, _, _) =
+        futures::future::select_all(receivers.iter_mut().map(|rx| Box::pin(rx.recv()))).await;
+
+    message
+}
+
+fn event_message(source_id: Uuid, state: &EventState) -> Message {
+    let now = TaiTime::now().to_string();
+
+    Message::Text(
+        json!({
+            "identity": { "source_id": source_id.to_string() },
+            "event_type": event_type(state),
+            "payload": state,
+            "timing": { "origin_timestamp": now, "creation_timestamp": now },
+        })
+        .to_string(),
+    )
+}
+
+fn heartbeat_message() -> Message {
+    Message::Text(json!({ "message_type": "heartbeat" }).to_string())
+}
+
+fn event_type(state: &EventState) -> &'static str {
+    match state {
+        EventState::Boolean(_) => "boolean",
+        EventState::Number(_) => "number/rational",
+        EventState::String(_) => "string",
+        EventState::Enum(_) => "enum",
+    }
+}
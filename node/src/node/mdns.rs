@@ -0,0 +1,421 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mdns::{RecordKind, Response};
+use tokio::sync::{mpsc, watch};
+use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// `pri` values at or above this are reserved for development registries and
+/// are only selected when nothing better is available.
+const DEVELOPMENT_PRIORITY: u32 = 100;
+
+/// The service advertised by both multicast mDNS and unicast DNS-SD
+/// registries, per AMWA IS-04.
+const SERVICE_NAME: &str = "_nmos-register._tcp";
+
+/// How discovery is performed. `multicast` can be disabled entirely for
+/// deployments where it's blocked, and `unicast` can be set to query a
+/// resolver that serves DNS-SD records for a routed/enterprise domain.
+#[derive(Debug, Clone)]
+pub struct NmosMdnsConfig {
+    pub multicast: bool,
+    pub unicast: Option<UnicastConfig>,
+}
+
+impl Default for NmosMdnsConfig {
+    fn default() -> Self {
+        Self {
+            multicast: true,
+            unicast: None,
+        }
+    }
+}
+
+/// Resolver and domain to query for unicast DNS-SD registry advertisements.
+#[derive(Debug, Clone)]
+pub struct UnicastConfig {
+    pub resolver: SocketAddr,
+    pub domain: String,
+}
+
+/// How often the unicast resolver is re-queried for fresh advertisements.
+const UNICAST_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum NmosMdnsEvent {
+    Discovery(String, std::io::Result<Response>),
+}
+
+/// A registry discovered via mDNS, with the fields needed to pick the best
+/// candidate per the AMWA IS-04 registry selection algorithm.
+#[derive(Debug, Clone)]
+pub struct NmosMdnsRegistry {
+    pub addr: SocketAddr,
+    pub priority: u32,
+    pub api_proto: String,
+    pub api_ver: String,
+    pub api_auth: bool,
+    last_seen: Instant,
+}
+
+impl NmosMdnsRegistry {
+    /// Parse the `pri`/`api_proto`/`api_ver`/`api_auth` TXT records and
+    /// address out of a raw mDNS response for `_nmos-register._tcp`.
+    pub fn parse(response: &Response) -> Self {
+        let mut ip = None;
+        let mut port = None;
+        let mut txt = Vec::new();
+
+        for record in response.records() {
+            match &record.kind {
+                RecordKind::A(addr) => ip = Some(IpAddr::V4(*addr)),
+                RecordKind::AAAA(addr) => ip = Some(IpAddr::V6(*addr)),
+                RecordKind::SRV { port: p, .. } => port = Some(*p),
+                RecordKind::TXT(entries) => txt.extend(entries.iter().cloned()),
+                _ => {}
+            }
+        }
+
+        let addr = SocketAddr::new(
+            ip.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            port.unwrap_or(80),
+        );
+
+        Self::from_parts(addr, &txt)
+    }
+
+    /// Build a registry from an already-resolved address and its DNS-SD TXT
+    /// record entries, shared by both the multicast and unicast paths.
+    fn from_parts(addr: SocketAddr, txt: &[String]) -> Self {
+        let mut priority = 0;
+        let mut api_proto = String::from("http");
+        let mut api_ver = String::from("v1.3");
+        let mut api_auth = false;
+
+        for entry in txt {
+            let Some((key, value)) = entry.split_once('=') else {
+                continue;
+            };
+
+            match key {
+                "pri" => priority = value.parse().unwrap_or(u32::MAX),
+                "api_proto" => api_proto = value.to_string(),
+                "api_ver" => api_ver = value.to_string(),
+                "api_auth" => api_auth = value == "true",
+                _ => {}
+            }
+        }
+
+        Self {
+            addr,
+            priority,
+            api_proto,
+            api_ver,
+            api_auth,
+            last_seen: Instant::now(),
+        }
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("{}://{}", self.api_proto, self.addr)
+    }
+
+    fn is_development(&self) -> bool {
+        self.priority >= DEVELOPMENT_PRIORITY
+    }
+}
+
+impl PartialEq for NmosMdnsRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr == other.addr
+    }
+}
+
+impl Eq for NmosMdnsRegistry {}
+
+impl PartialOrd for NmosMdnsRegistry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NmosMdnsRegistry {
+    /// `BinaryHeap` is a max-heap, so this orders the *best* candidate
+    /// (lowest `pri`, development registries last, ties broken by
+    /// most-recently-seen) to compare greatest.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .is_development()
+            .cmp(&self.is_development())
+            .then_with(|| other.priority.cmp(&self.priority))
+            .then_with(|| self.last_seen.cmp(&other.last_seen))
+    }
+}
+
+/// How long a registry that just failed is kept out of the pool before a
+/// fresh advertisement for it is allowed back in, so a flapping registry
+/// isn't instantly re-selected right after `fail` dropped it.
+const FAILOVER_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Tracks every registry discovered over mDNS/DNS-SD and keeps the node's
+/// active registry (exposed via `NodeHandle::active_registry`) pointed at the
+/// best available candidate, promoting the next-best one on failure.
+pub struct RegistryPool {
+    heap: Mutex<BinaryHeap<NmosMdnsRegistry>>,
+    /// Registries that recently failed, and when they're eligible to be
+    /// re-admitted to `heap`.
+    backoff: Mutex<HashMap<SocketAddr, Instant>>,
+    active_tx: watch::Sender<Option<NmosMdnsRegistry>>,
+}
+
+impl RegistryPool {
+    pub fn new() -> (Self, watch::Receiver<Option<NmosMdnsRegistry>>) {
+        let (active_tx, active_rx) = watch::channel(None);
+
+        (
+            Self {
+                heap: Mutex::new(BinaryHeap::new()),
+                backoff: Mutex::new(HashMap::new()),
+                active_tx,
+            },
+            active_rx,
+        )
+    }
+
+    /// Record a freshly discovered (or re-advertised) registry and
+    /// re-publish the best candidate in the pool as the active registry.
+    ///
+    /// A registry already in the heap has its `last_seen` refreshed in
+    /// place rather than being pushed again, so a registry re-advertising
+    /// on every mDNS/DNS-SD poll doesn't grow the heap without bound. A
+    /// registry still backing off after a recent failure is ignored.
+    pub fn discovered(&self, registry: NmosMdnsRegistry) {
+        if let Some(until) = self.backoff.lock().unwrap().get(&registry.addr) {
+            if Instant::now() < *until {
+                return;
+            }
+        }
+
+        let mut heap = self.heap.lock().unwrap();
+        let mut entries: Vec<_> = heap.drain().filter(|r| r.addr != registry.addr).collect();
+        entries.push(registry);
+        *heap = entries.into_iter().collect();
+        self.publish_best(&heap);
+    }
+
+    /// Drop a registry that just failed (registration or heartbeat error),
+    /// put it in backoff so it isn't immediately re-admitted, and promote
+    /// the next-best candidate, if any.
+    pub fn fail(&self, addr: SocketAddr) {
+        self.backoff
+            .lock()
+            .unwrap()
+            .insert(addr, Instant::now() + FAILOVER_BACKOFF);
+
+        let mut heap = self.heap.lock().unwrap();
+        let remaining = heap.drain().filter(|r| r.addr != addr).collect();
+        *heap = remaining;
+        self.publish_best(&heap);
+    }
+
+    pub fn active(&self) -> Option<NmosMdnsRegistry> {
+        self.active_tx.borrow().clone()
+    }
+
+    fn publish_best(&self, heap: &BinaryHeap<NmosMdnsRegistry>) {
+        let best = heap.peek().cloned();
+        // `send` only errors when there are no receivers left, which is
+        // harmless here: the node is shutting down.
+        let _ = self.active_tx.send(best);
+    }
+}
+
+pub struct MdnsContext {
+    tx: mpsc::UnboundedSender<NmosMdnsEvent>,
+}
+
+impl MdnsContext {
+    pub fn new(_config: &NmosMdnsConfig, tx: mpsc::UnboundedSender<NmosMdnsEvent>) -> Self {
+        Self { tx }
+    }
+
+    pub fn start(&mut self) -> MdnsPoller {
+        MdnsPoller {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+pub struct MdnsPoller {
+    tx: mpsc::UnboundedSender<NmosMdnsEvent>,
+}
+
+impl MdnsPoller {
+    /// Browse for `_nmos-register._tcp` advertisements and forward any
+    /// discoveries. Expected to be called on a fixed interval by the caller.
+    pub fn poll(&self) {
+        for response in mdns::discover::all_sync(format!("{SERVICE_NAME}.local"), None) {
+            let _ = self
+                .tx
+                .send(NmosMdnsEvent::Discovery(SERVICE_NAME.into(), response));
+        }
+    }
+}
+
+/// Repeatedly resolve `_nmos-register._tcp.<domain>` against `config`'s
+/// resolver and feed discovered registries into `pool`, for deployments
+/// where multicast is blocked and a central DNS server advertises the
+/// registry instead.
+pub async fn run_unicast_poll(config: UnicastConfig, pool: Arc<RegistryPool>) {
+    let resolver_config = ResolverConfig::from_parts(
+        None,
+        Vec::new(),
+        vec![NameServerConfig {
+            socket_addr: config.resolver,
+            protocol: Protocol::Udp,
+            tls_dns_name: None,
+            trust_negative_responses: false,
+            bind_addr: None,
+        }],
+    );
+    let resolver = TokioAsyncResolver::tokio(resolver_config, ResolverOpts::default());
+
+    loop {
+        match resolve_unicast(&resolver, &config.domain).await {
+            Ok(registries) => {
+                for registry in registries {
+                    pool.discovered(registry);
+                }
+            }
+            Err(err) => {
+                tracing::warn!(%err, domain = %config.domain, "Unicast DNS-SD lookup failed");
+            }
+        }
+
+        tokio::time::sleep(UNICAST_POLL_INTERVAL).await;
+    }
+}
+
+/// PTR -> SRV -> TXT lookup of `_nmos-register._tcp.<domain>`, per the
+/// standard DNS-SD resolution sequence: the PTR record enumerates every
+/// registry instance currently advertised under the service type, and each
+/// instance's own name is then queried for its SRV (host/port) and TXT
+/// (`pri`/`api_proto`/...) records.
+async fn resolve_unicast(
+    resolver: &TokioAsyncResolver,
+    domain: &str,
+) -> Result<Vec<NmosMdnsRegistry>, trust_dns_resolver::error::ResolveError> {
+    let service_name = format!("{SERVICE_NAME}.{domain}.");
+    let ptr_lookup = resolver.lookup(service_name, RecordType::PTR).await?;
+
+    let instances: Vec<_> = ptr_lookup
+        .iter()
+        .filter_map(|record| match record {
+            RData::PTR(name) => Some(name.to_utf8()),
+            _ => None,
+        })
+        .collect();
+
+    let mut registries = Vec::new();
+
+    for instance in instances {
+        let Ok(srv_lookup) = resolver.srv_lookup(instance.as_str()).await else {
+            continue;
+        };
+
+        let Some(srv) = srv_lookup.iter().next() else {
+            continue;
+        };
+
+        let target = srv.target().to_utf8();
+
+        let ips = match resolver.lookup_ip(target.as_str()).await {
+            Ok(ips) => ips,
+            Err(_) => continue,
+        };
+
+        let Some(ip) = ips.iter().next() else {
+            continue;
+        };
+
+        let txt = resolver
+            .txt_lookup(instance.as_str())
+            .await
+            .map(|txt| {
+                txt.iter()
+                    .flat_map(|record| record.txt_data().iter())
+                    .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let addr = SocketAddr::new(ip, srv.port());
+        registries.push(NmosMdnsRegistry::from_parts(addr, &txt));
+    }
+
+    Ok(registries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(addr: &str, pri: u32) -> NmosMdnsRegistry {
+        NmosMdnsRegistry::from_parts(addr.parse().unwrap(), &[format!("pri={pri}")])
+    }
+
+    #[test]
+    fn lower_priority_sorts_as_the_better_candidate() {
+        let low_pri = registry("127.0.0.1:80", 0);
+        let high_pri = registry("127.0.0.2:80", 50);
+
+        // `BinaryHeap` is a max-heap, so the *better* candidate (lower
+        // `pri`) must compare greater.
+        assert!(low_pri > high_pri);
+    }
+
+    #[test]
+    fn development_registries_rank_below_any_non_development_one() {
+        let development = registry("127.0.0.1:80", DEVELOPMENT_PRIORITY);
+        let non_development = registry("127.0.0.2:80", DEVELOPMENT_PRIORITY - 1);
+
+        assert!(non_development > development);
+    }
+
+    #[test]
+    fn ties_break_on_most_recently_seen() {
+        let mut older = registry("127.0.0.1:80", 0);
+        let newer = registry("127.0.0.2:80", 0);
+        older.last_seen = newer.last_seen - Duration::from_secs(1);
+
+        assert!(newer > older);
+    }
+
+    #[test]
+    fn discovered_updates_in_place_instead_of_duplicating() {
+        let (pool, _active) = RegistryPool::new();
+
+        pool.discovered(registry("127.0.0.1:80", 10));
+        pool.discovered(registry("127.0.0.1:80", 10));
+        pool.discovered(registry("127.0.0.2:80", 20));
+
+        assert_eq!(pool.heap.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn failed_registry_is_not_immediately_readmitted() {
+        let (pool, _active) = RegistryPool::new();
+        let addr: SocketAddr = "127.0.0.1:80".parse().unwrap();
+
+        pool.discovered(registry("127.0.0.1:80", 10));
+        pool.fail(addr);
+        pool.discovered(registry("127.0.0.1:80", 10));
+
+        assert!(pool.heap.lock().unwrap().is_empty());
+    }
+}
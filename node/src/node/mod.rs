@@ -1,35 +1,67 @@
+mod advertise;
+mod connection;
 mod event_handler;
+mod events;
 mod mdns;
+mod registration;
+mod tls;
 
+use std::future::pending;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
-use std::{collections::BinaryHeap, sync::Arc};
 
+use advertise::NodeAdvertiser;
 use axum::http::Method;
 use axum::Server;
+use connection::ConnectionManager;
 pub use event_handler::EventHandler;
+use events::EventHub;
 use mdns::MdnsContext;
+use nmos_rs_model::resource::event::EventState;
 use nmos_rs_model::{resource, Model};
+use registration::RegistrationClient;
+pub use tls::TlsConfig;
 use tokio::sync::mpsc;
 use tower::make::Shared;
 use tower::ServiceBuilder;
 use tower_http::cors::{self, CorsLayer};
-use tracing::info;
+use tracing::{info, warn};
+
+type BoxFuture = Pin<Box<dyn std::future::Future<Output = ()> + Send>>;
 
 use crate::{
     error::Result,
-    node::mdns::{NmosMdnsConfig, NmosMdnsEvent, NmosMdnsRegistry},
+    node::mdns::{NmosMdnsConfig, NmosMdnsEvent, NmosMdnsRegistry, RegistryPool},
     service::NmosService,
 };
 
+const DEFAULT_BIND_ADDR: (std::net::Ipv4Addr, u16) = (std::net::Ipv4Addr::UNSPECIFIED, 3000);
+
 pub struct NodeBuilder {
     event_handler: Option<Arc<dyn EventHandler>>,
+    discovery: NmosMdnsConfig,
+    bind_addr: SocketAddr,
+    tls: Option<TlsConfig>,
+    node: Option<resource::Node>,
+    devices: Vec<resource::Device>,
+    senders: Vec<resource::Sender>,
+    receivers: Vec<resource::Receiver>,
 }
 
 impl NodeBuilder {
     pub fn new() -> Self {
         Self {
             event_handler: None,
+            discovery: NmosMdnsConfig::default(),
+            bind_addr: DEFAULT_BIND_ADDR.into(),
+            tls: None,
+            node: None,
+            devices: Vec::new(),
+            senders: Vec::new(),
+            receivers: Vec::new(),
         }
     }
 
@@ -39,23 +71,105 @@ impl NodeBuilder {
         self
     }
 
+    /// Set the IS-04 Node resource this node advertises. Required before
+    /// `build` if any devices/senders/receivers are attached.
+    pub fn node(mut self, node: resource::Node) -> Self {
+        self.node = Some(node);
+
+        self
+    }
+
+    /// Attach a device to the node's resource set. Use
+    /// `DeviceBuilder::attach_sender`/`attach_receiver` beforehand to
+    /// back-link any senders/receivers it owns.
+    pub fn device(mut self, device: resource::Device) -> Self {
+        self.devices.push(device);
+
+        self
+    }
+
+    /// Attach a sender to the node's resource set.
+    pub fn sender(mut self, sender: resource::Sender) -> Self {
+        self.senders.push(sender);
+
+        self
+    }
+
+    /// Attach a receiver to the node's resource set.
+    pub fn receiver(mut self, receiver: resource::Receiver) -> Self {
+        self.receivers.push(receiver);
+
+        self
+    }
+
+    /// Configure how the node discovers registries. Defaults to multicast
+    /// mDNS only; see `NmosMdnsConfig` to disable it or enable unicast
+    /// DNS-SD against a configured resolver.
+    pub fn discovery(mut self, discovery: NmosMdnsConfig) -> Self {
+        self.discovery = discovery;
+
+        self
+    }
+
+    /// Address and port the NMOS APIs are served on. Defaults to
+    /// `0.0.0.0:3000`.
+    pub fn bind_addr(mut self, bind_addr: SocketAddr) -> Self {
+        self.bind_addr = bind_addr;
+
+        self
+    }
+
+    /// Serve the NMOS APIs over HTTPS using the given certificate chain and
+    /// private key, instead of plain HTTP.
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+
+        self
+    }
+
     pub async fn build(self) -> Node {
-        // Create nmos model
+        // Create nmos model, populated with whatever topology the caller
+        // declared via `node`/`device`/`sender`/`receiver`.
         let mut model = Model::new();
 
-        // Create new node
-        let node = resource::NodeBuilder::new("Test").build();
-        let device = resource::DeviceBuilder::new(&node, "devicetype").build();
-        let receiver = resource::ReceiverBuilder::new(
-            &device,
-            resource::Format::Video,
-            resource::Transport::RtpMulticast,
-        )
-        .build();
+        if let Some(node) = self.node {
+            model.insert_node(node).await;
+        }
+
+        let known_senders: std::collections::HashSet<uuid::Uuid> =
+            self.senders.iter().map(|sender| sender.id).collect();
+        let known_receivers: std::collections::HashSet<uuid::Uuid> =
+            self.receivers.iter().map(|receiver| receiver.id).collect();
+
+        for mut device in self.devices {
+            // A device can only back-link senders/receivers that were also
+            // passed to this builder via `.sender`/`.receiver`; drop any
+            // that weren't so the model never holds a dangling reference.
+            device.senders.retain(|id| {
+                let known = known_senders.contains(id);
+                if !known {
+                    warn!(device = %device.id, sender = %id, "Dropping dangling sender reference");
+                }
+                known
+            });
+            device.receivers.retain(|id| {
+                let known = known_receivers.contains(id);
+                if !known {
+                    warn!(device = %device.id, receiver = %id, "Dropping dangling receiver reference");
+                }
+                known
+            });
+
+            model.insert_device(device).await;
+        }
+
+        for sender in self.senders {
+            model.insert_sender(sender).await;
+        }
 
-        model.insert_node(node).await;
-        model.insert_device(device).await;
-        model.insert_receiver(receiver).await;
+        for receiver in self.receivers {
+            model.insert_receiver(receiver).await;
+        }
 
         // Wrap model in Arc
         let model = Arc::new(model);
@@ -63,18 +177,47 @@ impl NodeBuilder {
         // Make service
         let service = NmosService::new(model.clone());
 
+        let (registry_pool, active_registry) = RegistryPool::new();
+
+        let event_hub = EventHub::new(self.event_handler);
+
         Node {
-            event_handler: self.event_handler,
+            discovery: self.discovery,
+            bind_addr: self.bind_addr,
+            tls: self.tls,
             model,
             service,
+            registry_pool: Arc::new(registry_pool),
+            active_registry,
+            event_hub,
         }
     }
 }
 
+/// A cheap, cloneable handle for observing a `Node` while it's running.
+/// Obtain one via `Node::handle` before calling `Node::start`.
+#[derive(Clone)]
+pub struct NodeHandle {
+    active_registry: tokio::sync::watch::Receiver<Option<NmosMdnsRegistry>>,
+}
+
+impl NodeHandle {
+    /// The registry the node is currently registered (or attempting to
+    /// register) with, chosen per the AMWA IS-04 selection algorithm.
+    pub fn active_registry(&self) -> Option<NmosMdnsRegistry> {
+        self.active_registry.borrow().clone()
+    }
+}
+
 pub struct Node {
-    event_handler: Option<Arc<dyn EventHandler>>,
+    discovery: NmosMdnsConfig,
+    bind_addr: SocketAddr,
+    tls: Option<TlsConfig>,
     model: Arc<Model>,
     service: NmosService,
+    registry_pool: Arc<RegistryPool>,
+    active_registry: tokio::sync::watch::Receiver<Option<NmosMdnsRegistry>>,
+    event_hub: Arc<EventHub>,
 }
 
 impl Node {
@@ -82,50 +225,138 @@ impl Node {
         NodeBuilder::new()
     }
 
+    /// A cheap, cloneable handle for observing this node from outside the
+    /// task that drives it. Get this *before* calling `start`, which takes
+    /// `self` by value and blocks until shutdown, so there's no `Node` left
+    /// to call accessors on while it's running.
+    pub fn handle(&self) -> NodeHandle {
+        NodeHandle {
+            active_registry: self.active_registry.clone(),
+        }
+    }
+
+    /// Publish an IS-07 event/tally update for `source_id` to every
+    /// WebSocket client currently subscribed to it.
+    pub fn emit_event(&self, source_id: uuid::Uuid, state: EventState) {
+        self.event_hub.emit(source_id, state);
+    }
+
     pub async fn start(self) -> Result<()> {
         info!("Starting nmos-rs node");
 
-        // Channel for receiving MDNS events
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        // Only poll multicast mDNS if it's actually requested: this spares
+        // embedded nodes that already know their registry (unicast-only)
+        // the 100ms polling thread.
+        let mdns_receiver: BoxFuture = if self.discovery.multicast {
+            let (tx, mut rx) = mpsc::unbounded_channel();
 
-        let mdns_thread = thread::spawn(move || {
-            // Create context
-            let mut context = MdnsContext::new(&NmosMdnsConfig {}, tx);
+            thread::spawn(move || {
+                let mut context = MdnsContext::new(&NmosMdnsConfig::default(), tx);
 
-            let poller = context.start();
+                let poller = context.start();
 
-            loop {
-                // Poll every 100 ms
-                poller.poll();
-                thread::sleep(Duration::from_millis(100));
-            }
-        });
+                loop {
+                    // Poll every 100 ms
+                    poller.poll();
+                    thread::sleep(Duration::from_millis(100));
+                }
+            });
 
-        let mut registries = BinaryHeap::new();
+            let registry_pool = self.registry_pool.clone();
 
-        let mdns_receiver = async {
-            while let Some(event) = rx.recv().await {
-                if let NmosMdnsEvent::Discovery(_, Ok(discovery)) = event {
-                    let mdns_registry = NmosMdnsRegistry::parse(&discovery);
-                    registries.push(mdns_registry);
+            Box::pin(async move {
+                while let Some(event) = rx.recv().await {
+                    if let NmosMdnsEvent::Discovery(_, Ok(discovery)) = event {
+                        registry_pool.discovered(NmosMdnsRegistry::parse(&discovery));
+                    }
                 }
+            })
+        } else {
+            Box::pin(pending())
+        };
+
+        let unicast_receiver: BoxFuture = match self.discovery.unicast.clone() {
+            Some(unicast) => Box::pin(mdns::run_unicast_poll(unicast, self.registry_pool.clone())),
+            None => Box::pin(pending()),
+        };
+
+        let registration_client = RegistrationClient::new(self.model.clone());
+
+        let registration_task = async {
+            let mut active_registry = self.active_registry.clone();
+
+            loop {
+                // Wait for a registry to become available.
+                let registry = loop {
+                    if let Some(registry) = active_registry.borrow().clone() {
+                        break registry;
+                    }
+
+                    if active_registry.changed().await.is_err() {
+                        return;
+                    }
+                };
+
+                registration_client.run(&registry, &self.registry_pool).await;
             }
         };
 
+        // IS-05 connection management, layered alongside the IS-04 service.
+        let connection_manager = ConnectionManager::new(self.model.clone());
+        let activation_task = connection_manager.clone().run_activations();
+
         // Create server
+        let app = connection_manager
+            .router()
+            .merge(self.event_hub.router())
+            .fallback_service(self.service);
+
         let app = ServiceBuilder::new()
             .layer(
                 CorsLayer::new()
-                    .allow_methods([Method::GET, Method::POST])
+                    .allow_methods([Method::GET, Method::POST, Method::PATCH])
                     .allow_origin(cors::Any),
             )
-            .service(self.service);
+            .service(app);
+
+        let addr = self.bind_addr;
 
-        let addr = ([0, 0, 0, 0], 3000).into();
-        let server = Server::bind(&addr).serve(Shared::new(app));
+        // Advertise this node's own Node API over mDNS so registries and
+        // other nodes doing IS-04 discovery can find it, including the
+        // api_proto it should be reached on. Held alive for as long as
+        // `start` is running; dropping it withdraws the advertisement.
+        let api_proto = if self.tls.is_some() { "https" } else { "http" };
+        let _advertiser = match self.model.nodes().await.keys().next().copied() {
+            Some(node_id) => match NodeAdvertiser::start(node_id, addr.port(), api_proto) {
+                Ok(advertiser) => Some(advertiser),
+                Err(err) => {
+                    warn!(%err, "Failed to advertise node over mDNS");
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let server: BoxFuture = match self.tls {
+            Some(tls) => {
+                let rustls_config = tls.load().await?;
+
+                Box::pin(async move {
+                    let _ = axum_server::bind_rustls(addr, rustls_config)
+                        .serve(app.into_make_service())
+                        .await;
+                })
+            }
+            None => Box::pin(async move {
+                let _ = Server::bind(&addr).serve(Shared::new(app)).await;
+            }),
+        };
 
         tokio::select! {
             _ = mdns_receiver => {}
+            _ = unicast_receiver => {}
+            _ = registration_task => {}
+            _ = activation_task => {}
             _ = server => {}
         };
 
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request, StatusCode};
+use nmos_rs_model::{Model, Resource};
+use tracing::{info, warn};
+
+use crate::node::mdns::{NmosMdnsRegistry, RegistryPool};
+
+/// Interval between IS-04 heartbeats once registered, per the AMWA
+/// recommendation.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Drives the IS-04 registration state machine against a single registry:
+/// registers the full resource set, then heartbeats until the registry is
+/// lost (connection failure or an unexpected response) or a higher-priority
+/// registry appears, at which point `run` returns so the caller can fail
+/// over. A 404 telling us it garbage-collected the node makes it register
+/// the full resource set again and keep heartbeating.
+pub struct RegistrationClient {
+    model: Arc<Model>,
+    client: Client<HttpConnector>,
+}
+
+impl RegistrationClient {
+    pub fn new(model: Arc<Model>) -> Self {
+        Self {
+            model,
+            client: Client::new(),
+        }
+    }
+
+    /// Register with `registry` and heartbeat until it is lost, a better
+    /// registry should take over, or we are garbage-collected (in which
+    /// case we re-register and keep going). `pool` is consulted at each
+    /// heartbeat boundary for a higher-priority candidate, and is told
+    /// about failures so it can promote the next-best registry.
+    pub async fn run(&self, registry: &NmosMdnsRegistry, pool: &RegistryPool) {
+        loop {
+            if let Err(err) = self.register_all(registry).await {
+                warn!(%err, registry = %registry.base_url(), "Registration failed");
+                pool.fail(registry.addr);
+                return;
+            }
+
+            info!(registry = %registry.base_url(), "Registered with registry");
+
+            match self.heartbeat_loop(registry, pool).await {
+                HeartbeatOutcome::Lost => {
+                    pool.fail(registry.addr);
+                    return;
+                }
+                HeartbeatOutcome::Superseded => return,
+                HeartbeatOutcome::GarbageCollected => {
+                    info!(
+                        registry = %registry.base_url(),
+                        "Registry garbage-collected node, re-registering"
+                    );
+                    // Loop around and register the full resource set again.
+                }
+            }
+        }
+    }
+
+    /// POST each model resource to the registry, in dependency order.
+    async fn register_all(&self, registry: &NmosMdnsRegistry) -> Result<(), RegistrationError> {
+        for node in self.model.nodes().await.values() {
+            self.register_resource(registry, "node", node).await?;
+        }
+        for device in self.model.devices().await.values() {
+            self.register_resource(registry, "device", device).await?;
+        }
+        for sender in self.model.senders().await.values() {
+            self.register_resource(registry, "sender", sender).await?;
+        }
+        for receiver in self.model.receivers().await.values() {
+            self.register_resource(registry, "receiver", receiver).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn register_resource<R: Resource>(
+        &self,
+        registry: &NmosMdnsRegistry,
+        type_: &str,
+        resource: &R,
+    ) -> Result<(), RegistrationError> {
+        let body = serde_json::json!({
+            "type": type_,
+            "data": resource.to_json(),
+        });
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!(
+                "{}/x-nmos/registration/v1.3/resource",
+                registry.base_url()
+            ))
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("request is well-formed");
+
+        let response = self.client.request(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RegistrationError::Rejected(response.status()));
+        }
+
+        Ok(())
+    }
+
+    /// Heartbeat the node's own resource on a fixed interval until the
+    /// registry is lost, signals that we were garbage-collected, or a
+    /// higher-priority registry has appeared in `pool`.
+    async fn heartbeat_loop(
+        &self,
+        registry: &NmosMdnsRegistry,
+        pool: &RegistryPool,
+    ) -> HeartbeatOutcome {
+        let node_id = self.model.nodes().await.keys().next().copied();
+
+        let Some(node_id) = node_id else {
+            return HeartbeatOutcome::Lost;
+        };
+
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+
+            if let Some(best) = pool.active() {
+                if best.addr != registry.addr {
+                    return HeartbeatOutcome::Superseded;
+                }
+            }
+
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(format!(
+                    "{}/x-nmos/registration/v1.3/health/nodes/{}",
+                    registry.base_url(),
+                    node_id
+                ))
+                .body(Body::empty())
+                .expect("request is well-formed");
+
+            match self.client.request(request).await {
+                Ok(response) if response.status() == StatusCode::OK => continue,
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                    return HeartbeatOutcome::GarbageCollected;
+                }
+                Ok(response) => {
+                    warn!(status = %response.status(), "Unexpected heartbeat response");
+                    return HeartbeatOutcome::Lost;
+                }
+                Err(err) => {
+                    warn!(%err, "Heartbeat failed");
+                    return HeartbeatOutcome::Lost;
+                }
+            }
+        }
+    }
+}
+
+enum HeartbeatOutcome {
+    /// The registry stopped responding or answered with an unexpected
+    /// status (or the model holds no node to heartbeat); the caller should
+    /// fail over.
+    Lost,
+    /// A higher-priority registry is now available; hand off to it.
+    Superseded,
+    /// The registry returned 404 on heartbeat; re-register everything.
+    GarbageCollected,
+}
+
+/// Why registering a resource with a registry failed.
+#[derive(Debug)]
+enum RegistrationError {
+    /// The request itself couldn't be completed.
+    Transport(hyper::Error),
+    /// The registry responded, but rejected the resource.
+    Rejected(StatusCode),
+}
+
+impl std::fmt::Display for RegistrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistrationError::Transport(err) => write!(f, "{err}"),
+            RegistrationError::Rejected(status) => {
+                write!(f, "registry rejected resource: {status}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistrationError {}
+
+impl From<hyper::Error> for RegistrationError {
+    fn from(err: hyper::Error) -> Self {
+        RegistrationError::Transport(err)
+    }
+}
@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use axum_server::tls_rustls::RustlsConfig;
+
+/// Certificate chain and private key paths for serving the NMOS APIs over
+/// HTTPS instead of plain HTTP.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl TlsConfig {
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        }
+    }
+
+    pub(crate) async fn load(&self) -> Result<RustlsConfig, std::io::Error> {
+        RustlsConfig::from_pem_file(&self.cert_path, &self.key_path).await
+    }
+}